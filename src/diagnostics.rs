@@ -0,0 +1,112 @@
+// Copyright 2020 Sebastian Wiesner <sebastian@swsnr.de>
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A structured audit of the `$HOME` install state.
+
+use anyhow::Error;
+use fehler::throws;
+use serde::{Deserialize, Serialize};
+use versions::Versioning;
+
+use crate::manifest::ManifestStore;
+use crate::tools::{manpath, path_contains};
+use crate::version_spec::VersionSpec;
+use crate::{installed_files, installed_manifest_version, InstallDirs};
+
+/// Whether an install directory is wired into the relevant environment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirStatus {
+    /// The directory being checked.
+    pub path: std::path::PathBuf,
+    /// Whether it is contained in the corresponding environment variable.
+    pub in_environment: bool,
+}
+
+/// The diagnosis of a single tool in the store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolStatus {
+    /// The name of the tool.
+    pub name: String,
+    /// The version the manifest declares.
+    pub declared: VersionSpec,
+    /// The installed version, if the tool is installed.
+    pub installed: Option<Versioning>,
+    /// Whether the installed version is outdated.
+    pub outdated: bool,
+    /// Files the manifest installs that are missing despite a recorded install.
+    pub missing_files: Vec<std::path::PathBuf>,
+}
+
+impl ToolStatus {
+    /// Whether this is a broken install: a recorded version but missing files.
+    pub fn is_broken(&self) -> bool {
+        self.installed.is_some() && !self.missing_files.is_empty()
+    }
+}
+
+/// A machine-readable audit of the whole install state.
+///
+/// Inspired by millennium-cli's `info` command: describe the bin/man
+/// directories and, for every manifest in a [`ManifestStore`], the installed
+/// and declared versions and whether the install is outdated, missing, or
+/// broken.  The report serializes to JSON for scripting or can be rendered as
+/// a human-readable table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    /// Status of the binary directory.
+    pub bin_dir: DirStatus,
+    /// Status of the man page directory.
+    pub man_dir: DirStatus,
+    /// Per-tool status, in store order.
+    pub tools: Vec<ToolStatus>,
+}
+
+/// Audit every manifest in `store` against `install_dirs`.
+#[throws]
+pub fn diagnose(install_dirs: &InstallDirs, store: &ManifestStore) -> Report {
+    let bin_dir = DirStatus {
+        path: install_dirs.bin_dir().to_path_buf(),
+        in_environment: std::env::var_os("PATH")
+            .map(|path| path_contains(&path, install_dirs.bin_dir()))
+            .unwrap_or(false),
+    };
+    let man_dir = DirStatus {
+        path: install_dirs.man_dir().to_path_buf(),
+        in_environment: path_contains(&manpath()?, install_dirs.man_dir()),
+    };
+
+    let mut tools = Vec::new();
+    for manifest in store.manifests()? {
+        let installed = installed_manifest_version(install_dirs, &manifest)?;
+        let missing_files = if installed.is_some() {
+            installed_files(install_dirs, &manifest)
+                .into_iter()
+                .filter(|file| !file.exists())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        // Outdated when we cannot confirm the installed version satisfies the
+        // spec: an unsatisfied requirement, or a moving target we must re-check.
+        let outdated = installed
+            .as_ref()
+            .map(|version| manifest.info.version.satisfies(version) != Some(true))
+            .unwrap_or(false);
+        tools.push(ToolStatus {
+            name: manifest.info.name.clone(),
+            declared: manifest.info.version.clone(),
+            installed,
+            outdated,
+            missing_files,
+        });
+    }
+
+    Report {
+        bin_dir,
+        man_dir,
+        tools,
+    }
+}
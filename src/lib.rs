@@ -20,6 +20,7 @@ use versions::Versioning;
 
 pub use dirs::*;
 pub use manifest::{Manifest, ManifestRepo, ManifestStore};
+pub use progress::{default_reporter, BarReporter, ProgressReporter, SilentReporter};
 pub use repos::HomebinRepos;
 
 use crate::operations::{ApplyOperation, RemoveOperation};
@@ -28,9 +29,21 @@ use crate::tools::{manpath, path_contains};
 mod checksum;
 mod dirs;
 mod process;
+mod progress;
 mod repos;
 mod tools;
+mod state;
+mod transaction;
+mod version_spec;
 
+pub use state::InUse;
+
+pub use version_spec::VersionSpec;
+
+use crate::transaction::InstallTransaction;
+
+/// Structured diagnostics for the install state.
+pub mod diagnostics;
 /// Manifest types and loading.
 pub mod manifest;
 /// Operations to apply manifests to a home directory.
@@ -82,8 +95,9 @@ pub fn install_manifest(
     dirs: &HomebinProjectDirs,
     install_dirs: &mut InstallDirs,
     manifest: &Manifest,
+    reporter: &dyn ProgressReporter,
 ) -> () {
-    let op_dirs = ManifestOperationDirs::for_manifest(dirs, install_dirs, manifest)?;
+    let op_dirs = ManifestOperationDirs::for_manifest(dirs, install_dirs, manifest, reporter)?;
     let operations = operations::install_manifest(manifest);
     std::fs::create_dir_all(op_dirs.download_dir()).with_context(|| {
         format!(
@@ -92,9 +106,68 @@ pub fn install_manifest(
         )
     })?;
 
+    let mut transaction = InstallTransaction::new();
     for operation in operations {
+        for destination in operations::operation_destinations(std::slice::from_ref(&operation).iter())
+        {
+            let path = install_dirs
+                .path(destination.directory())
+                .join(destination.name());
+            transaction.register(&path)?;
+        }
         operation.apply_operation(&op_dirs)?;
     }
+
+    // Resolve the version that was just installed, move its files into the
+    // versioned tree at `<data>/versions/<name>/<version>/...` and activate
+    // it, so multiple versions live side by side and `bin_dir()`/`man_dir()`
+    // entries become symlinks into the active version.  `activate_version`
+    // also records the resolved version in the in-use state file, which later
+    // "what's outdated?" scans consult instead of re-running the binary.
+    //
+    // The move/activate phase is registered with the same transaction and we
+    // only `commit` once activation succeeds, so a failure mid-way rolls the
+    // whole install back rather than leaving files half-moved.
+    //
+    // A version is mandatory: without one we could not place the files under a
+    // versioned directory and the install would be invisible to the
+    // multi-version API (`installed_versions`, `remove_manifest`).  Fail here
+    // and let the transaction undo the install rather than leave an
+    // unmanageable flat one behind.
+    let version = installed_manifest_version(install_dirs, manifest)?.ok_or_else(|| {
+        anyhow!(
+            "Version check for {} did not yield a version; refusing to leave an unmanaged install",
+            manifest.info.name
+        )
+    })?;
+    for destination in
+        operations::operation_destinations(operations::install_manifest(manifest).iter())
+    {
+        let flat = install_dirs
+            .path(destination.directory())
+            .join(destination.name());
+        let versioned = install_dirs
+            .versioned_path(&manifest.info.name, &version, destination.directory())
+            .join(destination.name());
+        transaction.register(&versioned)?;
+        if let Some(parent) = versioned.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create directory {}", parent.display())
+            })?;
+        }
+        std::fs::rename(&flat, &versioned).with_context(|| {
+            format!(
+                "Failed to move {} into versioned tree at {}",
+                flat.display(),
+                versioned.display()
+            )
+        })?;
+        // The activation symlink recreated at the flat path is also part of
+        // this transaction.
+        transaction.register(&flat)?;
+    }
+    activate_version(install_dirs, manifest, &version)?;
+    transaction.commit();
 }
 
 /// Remove a manifest.
@@ -105,12 +178,96 @@ pub fn remove_manifest(
     dirs: &HomebinProjectDirs,
     install_dirs: &mut InstallDirs,
     manifest: &Manifest,
+    version: &Versioning,
 ) -> () {
-    let op_dirs = ManifestOperationDirs::for_manifest(dirs, install_dirs, manifest)?;
+    let op_dirs = ManifestOperationDirs::for_version(dirs, install_dirs, manifest, version)?;
     let operations = operations::remove_manifest(manifest);
     for operation in operations {
         operation.apply_operation(&op_dirs)?;
     }
+    std::fs::remove_dir_all(install_dirs.versioned_root(&manifest.info.name, version)).ok();
+
+    // Drop the active symlinks and clear the in-use marker if we just removed
+    // the active version, so no links are left dangling into the deleted tree.
+    let in_use = InUse::load(install_dirs.data_dir())?;
+    if in_use.get(&manifest.info.name) == Some(version) {
+        deactivate(install_dirs, manifest)?;
+    }
+}
+
+/// Make `version` of `manifest` the active version.
+///
+/// Rewrite the entries in `bin_dir()`/`man_dir()` to symlinks pointing into
+/// the versioned install tree at `<data>/versions/<name>/<version>/...` and
+/// record the selected version in the in-use state file.  The version must
+/// already be installed; see [`install_manifest`].
+#[throws]
+pub fn activate_version(install_dirs: &InstallDirs, manifest: &Manifest, version: &Versioning) -> () {
+    let name = &manifest.info.name;
+    for destination in operations::operation_destinations(operations::install_manifest(manifest).iter())
+    {
+        let target = install_dirs
+            .versioned_path(name, version, destination.directory())
+            .join(destination.name());
+        let link = install_dirs
+            .path(destination.directory())
+            .join(destination.name());
+        if let Some(parent) = link.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create directory {}", parent.display())
+            })?;
+        }
+        if link.exists() || std::fs::symlink_metadata(&link).is_ok() {
+            std::fs::remove_file(&link)
+                .with_context(|| format!("Failed to replace {}", link.display()))?;
+        }
+        std::os::unix::fs::symlink(&target, &link).with_context(|| {
+            format!("Failed to link {} to {}", link.display(), target.display())
+        })?;
+    }
+
+    let mut in_use = InUse::load(install_dirs.data_dir())?;
+    in_use.set_in_use(name, version.clone());
+    in_use.save(install_dirs.data_dir())?;
+}
+
+/// Deactivate `manifest`, removing its active symlinks.
+///
+/// Remove the symlinks in `bin_dir()`/`man_dir()` that point into the active
+/// version's tree and clear the in-use marker.  The versioned install trees
+/// themselves are left in place; use [`remove_manifest`] to delete a version.
+#[throws]
+pub fn deactivate(install_dirs: &InstallDirs, manifest: &Manifest) -> () {
+    for destination in
+        operations::operation_destinations(operations::install_manifest(manifest).iter())
+    {
+        let link = install_dirs
+            .path(destination.directory())
+            .join(destination.name());
+        if std::fs::symlink_metadata(&link).is_ok() {
+            std::fs::remove_file(&link)
+                .with_context(|| format!("Failed to unlink {}", link.display()))?;
+        }
+    }
+
+    let mut in_use = InUse::load(install_dirs.data_dir())?;
+    if in_use.clear(&manifest.info.name).is_some() {
+        in_use.save(install_dirs.data_dir())?;
+    }
+}
+
+/// The versions of `name` installed under `<data>/versions/<name>`.
+pub fn installed_versions(install_dirs: &InstallDirs, name: &str) -> Vec<Versioning> {
+    let dir = install_dirs.versions_dir().join(name);
+    let mut versions: Vec<Versioning> = std::fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().and_then(Versioning::new))
+        .collect();
+    versions.sort();
+    versions
 }
 
 /// Get the installed version of the given manifest.
@@ -170,11 +327,55 @@ pub fn installed_manifest_version(dirs: &InstallDirs, manifest: &Manifest) -> Op
 
 /// Whether the given manifest is outdated and needs updating.
 ///
-/// Return the installed version if it's outdated, otherwise return None.
+/// The installed version is outdated when it fails to satisfy the version
+/// specification in `manifest.info.version` (a range or channel), rather than
+/// being strictly less than a single pinned version.  Return the installed
+/// version if it's outdated, otherwise return `None`.
 #[throws]
 pub fn outdated_manifest_version(dirs: &InstallDirs, manifest: &Manifest) -> Option<Versioning> {
+    // A version we cannot confirm as satisfying the spec (an unsatisfied
+    // requirement, or a moving `latest`/LTS target whose answer is unknown) is
+    // reported as outdated so the caller re-checks it.
     installed_manifest_version(dirs, manifest)?
-        .filter(|installed| installed < &manifest.info.version)
+        .filter(|installed| manifest.info.version.satisfies(installed) != Some(true))
+}
+
+/// Find the outdated manifests in `store` without re-running binaries where possible.
+///
+/// For every manifest in `store`, compare the installed version against its
+/// declared requirement.  Following wasmer's `get_if_package_has_new_version`,
+/// consult the version cache populated by [`install_manifest`] first.  A cache
+/// entry is only trusted when it can be confirmed as satisfying a concrete
+/// requirement; the cache is authoritative for that case and is invalidated by
+/// the next [`install_manifest`].  When the cache holds no entry, or the entry
+/// no longer satisfies the requirement, or the requirement is a moving
+/// `latest`/LTS target that cannot be confirmed from the cache, fall back to
+/// invoking the binary (via [`outdated_manifest_version`]) so an out-of-band
+/// upgrade or downgrade is still detected.  This keeps the common up-to-date
+/// scan nearly instantaneous, offline, and independent of `$PATH`.
+///
+/// Return each outdated manifest together with its installed version, if known.
+#[throws]
+pub fn outdated(
+    store: &ManifestStore,
+    install_dirs: &InstallDirs,
+) -> Vec<(Manifest, Option<Versioning>)> {
+    let cache = InUse::load(install_dirs.data_dir())?;
+    let mut outdated = Vec::new();
+    for manifest in store.manifests()? {
+        let cached = cache.get(&manifest.info.name);
+        // Trust the cache only when it confirms a concrete requirement is met.
+        let confirmed =
+            cached.map(|installed| manifest.info.version.satisfies(installed)) == Some(Some(true));
+        if confirmed {
+            continue;
+        }
+        // Cache miss, stale, or unconfirmable: re-check against the binary.
+        if let Some(installed) = outdated_manifest_version(install_dirs, &manifest)? {
+            outdated.push((manifest, Some(installed)));
+        }
+    }
+    outdated
 }
 
 /// Get all files the `manifest` would install to `dirs`.
@@ -0,0 +1,99 @@
+// Copyright 2020 Sebastian Wiesner <sebastian@swsnr.de>
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Progress reporting for downloads.
+
+use std::io::IsTerminal;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Report progress of a download.
+///
+/// Threaded through [`ApplyOperation::apply_operation`] via
+/// [`ManifestOperationDirs`] so the core library stays UI-agnostic: an
+/// interactive front-end renders a live byte-count bar while piped or CI
+/// output gets the silent reporter and stays clean.
+///
+/// [`ApplyOperation::apply_operation`]: crate::operations::ApplyOperation::apply_operation
+/// [`ManifestOperationDirs`]: crate::ManifestOperationDirs
+pub trait ProgressReporter {
+    /// Start reporting a download of `total` bytes (unknown if `None`).
+    fn start(&self, total: Option<u64>);
+    /// Advance the reported position to `position` bytes.
+    fn advance(&self, position: u64);
+    /// Finish reporting.
+    fn finish(&self);
+}
+
+/// A reporter that discards all progress, for non-interactive output.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SilentReporter;
+
+impl ProgressReporter for SilentReporter {
+    fn start(&self, _total: Option<u64>) {}
+    fn advance(&self, _position: u64) {}
+    fn finish(&self) {}
+}
+
+/// A reporter rendering a live byte-count bar with indicatif.
+#[derive(Debug)]
+pub struct BarReporter {
+    bar: ProgressBar,
+}
+
+impl BarReporter {
+    /// Create a reporter rendering to stderr.
+    pub fn new() -> BarReporter {
+        BarReporter {
+            bar: ProgressBar::hidden(),
+        }
+    }
+}
+
+impl Default for BarReporter {
+    fn default() -> BarReporter {
+        BarReporter::new()
+    }
+}
+
+impl ProgressReporter for BarReporter {
+    fn start(&self, total: Option<u64>) {
+        match total {
+            Some(total) => {
+                self.bar.set_length(total);
+                self.bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template("{bytes}/{total_bytes} [{bar:40}] {bytes_per_sec}")
+                        .progress_chars("=> "),
+                );
+            }
+            None => self.bar.set_style(ProgressStyle::default_spinner()),
+        }
+        self.bar.set_draw_target(indicatif::ProgressDrawTarget::stderr());
+    }
+
+    fn advance(&self, position: u64) {
+        self.bar.set_position(position);
+    }
+
+    fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// Select a progress reporter for the current output.
+///
+/// Return the silent reporter when `quiet` is set or stderr is not a terminal,
+/// following wasmer's approach, and a live bar otherwise.  The check matches
+/// the bar's draw target (stderr) so piping stdout does not suppress a bar that
+/// would render fine, nor draw one into a pipe.
+pub fn default_reporter(quiet: bool) -> Box<dyn ProgressReporter> {
+    if quiet || !std::io::stderr().is_terminal() {
+        Box::new(SilentReporter)
+    } else {
+        Box::new(BarReporter::new())
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2020 Sebastian Wiesner <sebastian@swsnr.de>
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Track which installed version of each tool is currently in use.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use fehler::throws;
+use serde::{Deserialize, Serialize};
+use versions::Versioning;
+
+/// The name of the state file holding the in-use record.
+const STATE_FILE: &str = "in-use.json";
+
+/// The version of each tool that is currently active.
+///
+/// Persisted next to the versioned install trees as a small JSON file,
+/// analogous to zupper's `in_use` tracking: `setInUse` records the active
+/// version of a tool and `save` writes the map back to disk.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InUse {
+    /// Active version keyed by tool name.
+    #[serde(default)]
+    versions: BTreeMap<String, Versioning>,
+}
+
+impl InUse {
+    /// Load the in-use record from `data_dir`, or an empty record if absent.
+    #[throws]
+    pub fn load(data_dir: &Path) -> InUse {
+        let path = state_file(data_dir);
+        if path.is_file() {
+            let contents = std::fs::read(&path)
+                .with_context(|| format!("Failed to read state file {}", path.display()))?;
+            serde_json::from_slice(&contents)
+                .with_context(|| format!("Failed to parse state file {}", path.display()))?
+        } else {
+            InUse::default()
+        }
+    }
+
+    /// The active version of `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&Versioning> {
+        self.versions.get(name)
+    }
+
+    /// Record `version` as the active version of `name`.
+    pub fn set_in_use(&mut self, name: &str, version: Versioning) {
+        self.versions.insert(name.to_string(), version);
+    }
+
+    /// Clear the active version of `name`, returning it if one was set.
+    pub fn clear(&mut self, name: &str) -> Option<Versioning> {
+        self.versions.remove(name)
+    }
+
+    /// Persist this record to the state file in `data_dir`.
+    #[throws]
+    pub fn save(&self, data_dir: &Path) {
+        std::fs::create_dir_all(data_dir).with_context(|| {
+            format!("Failed to create state directory {}", data_dir.display())
+        })?;
+        let path = state_file(data_dir);
+        let contents = serde_json::to_vec_pretty(self)
+            .with_context(|| format!("Failed to serialize state file {}", path.display()))?;
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write state file {}", path.display()))?;
+    }
+}
+
+/// The path of the state file within `data_dir`.
+fn state_file(data_dir: &Path) -> PathBuf {
+    data_dir.join(STATE_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn temp_dir() -> PathBuf {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "homebin-state-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn version(s: &str) -> Versioning {
+        Versioning::new(s).unwrap()
+    }
+
+    #[test]
+    fn load_is_empty_when_absent() {
+        let dir = temp_dir();
+        assert!(InUse::load(&dir).unwrap().get("rg").is_none());
+    }
+
+    #[test]
+    fn set_save_and_load_round_trips() {
+        let dir = temp_dir();
+        let mut in_use = InUse::default();
+        in_use.set_in_use("rg", version("13.0.0"));
+        in_use.save(&dir).unwrap();
+
+        let loaded = InUse::load(&dir).unwrap();
+        assert_eq!(loaded.get("rg"), Some(&version("13.0.0")));
+    }
+
+    #[test]
+    fn clear_removes_the_entry() {
+        let dir = temp_dir();
+        let mut in_use = InUse::default();
+        in_use.set_in_use("rg", version("13.0.0"));
+        assert_eq!(in_use.clear("rg"), Some(version("13.0.0")));
+        assert!(in_use.clear("rg").is_none());
+        assert!(in_use.get("rg").is_none());
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2020 Sebastian Wiesner <sebastian@swsnr.de>
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Rollback for partially applied installs.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use fehler::throws;
+
+/// Suffix appended to a destination to name the backup of an overwritten file.
+const BACKUP_SUFFIX: &str = ".homebin-rollback";
+
+/// A guard that undoes a partial install unless explicitly committed.
+///
+/// Modeled on cargo's install `Transaction`: before each operation writes a
+/// destination the caller records it through [`register`](Self::register),
+/// which backs up any file it is about to overwrite and remembers freshly
+/// created files and directories.  Once every operation applied cleanly the
+/// caller invokes [`commit`](Self::commit) to disarm the guard; otherwise the
+/// `Drop` implementation deletes the files it created, removes the directories
+/// it freshly created, and restores overwritten files from their backup,
+/// leaving the install dirs as they were before the transaction started.
+pub struct InstallTransaction {
+    /// Files freshly created by this transaction, to delete on rollback.
+    created_files: Vec<PathBuf>,
+    /// Overwritten files as `(destination, backup)`, restored on rollback.
+    backups: Vec<(PathBuf, PathBuf)>,
+    /// Directories freshly created by this transaction, removed on rollback.
+    created_dirs: Vec<PathBuf>,
+    /// Whether [`commit`](Self::commit) disarmed the guard.
+    committed: bool,
+}
+
+impl InstallTransaction {
+    /// Create an empty transaction.
+    pub fn new() -> InstallTransaction {
+        InstallTransaction {
+            created_files: Vec::new(),
+            backups: Vec::new(),
+            created_dirs: Vec::new(),
+            committed: false,
+        }
+    }
+
+    /// Record a `destination` an operation is about to write.
+    ///
+    /// Remember every parent directory that does not exist yet so rollback can
+    /// remove it, and back up `destination` if it already exists so rollback
+    /// can restore its previous contents.  Call this *before* the operation
+    /// writes the file.
+    #[throws]
+    pub fn register(&mut self, destination: &Path) {
+        let mut missing = Vec::new();
+        let mut ancestor = destination.parent();
+        while let Some(dir) = ancestor {
+            if dir.as_os_str().is_empty() || dir.exists() {
+                break;
+            }
+            missing.push(dir.to_path_buf());
+            ancestor = dir.parent();
+        }
+        // Record outermost-first so rollback can remove innermost-first.
+        for dir in missing.into_iter().rev() {
+            self.created_dirs.push(dir);
+        }
+
+        if destination.exists() {
+            let backup = backup_path(destination);
+            std::fs::copy(destination, &backup).with_context(|| {
+                format!(
+                    "Failed to back up {} to {} before overwriting",
+                    destination.display(),
+                    backup.display()
+                )
+            })?;
+            self.backups.push((destination.to_path_buf(), backup));
+        } else {
+            self.created_files.push(destination.to_path_buf());
+        }
+    }
+
+    /// Commit the transaction, disarming the rollback guard.
+    ///
+    /// Disarm the guard first so a fully-successful install is never rolled
+    /// back, then discard all backups of overwritten files on a best-effort
+    /// basis; from now on `Drop` is a no-op.
+    pub fn commit(mut self) {
+        self.committed = true;
+        for (_, backup) in &self.backups {
+            if backup.exists() {
+                let _ = std::fs::remove_file(backup);
+            }
+        }
+    }
+}
+
+impl Default for InstallTransaction {
+    fn default() -> InstallTransaction {
+        InstallTransaction::new()
+    }
+}
+
+impl Drop for InstallTransaction {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+
+        // Delete freshly created files.
+        for file in &self.created_files {
+            let _ = std::fs::remove_file(file);
+        }
+        // Restore overwritten files from their backups.
+        for (destination, backup) in &self.backups {
+            let _ = std::fs::rename(backup, destination);
+        }
+        // Remove freshly created directories, innermost first.
+        for dir in self.created_dirs.iter().rev() {
+            let _ = std::fs::remove_dir(dir);
+        }
+    }
+}
+
+/// The backup path for `destination`.
+fn backup_path(destination: &Path) -> PathBuf {
+    let mut name = destination.as_os_str().to_os_string();
+    name.push(BACKUP_SUFFIX);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// A fresh, empty temporary directory unique to the calling test.
+    fn temp_dir() -> PathBuf {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "homebin-txn-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rollback_removes_created_file() {
+        let dir = temp_dir();
+        let dest = dir.join("binary");
+        {
+            let mut transaction = InstallTransaction::new();
+            transaction.register(&dest).unwrap();
+            std::fs::write(&dest, b"new").unwrap();
+            // Dropped without commit.
+        }
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn commit_keeps_created_file() {
+        let dir = temp_dir();
+        let dest = dir.join("binary");
+        let mut transaction = InstallTransaction::new();
+        transaction.register(&dest).unwrap();
+        std::fs::write(&dest, b"new").unwrap();
+        transaction.commit();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"new");
+    }
+
+    #[test]
+    fn rollback_restores_overwritten_file() {
+        let dir = temp_dir();
+        let dest = dir.join("binary");
+        std::fs::write(&dest, b"old").unwrap();
+        {
+            let mut transaction = InstallTransaction::new();
+            transaction.register(&dest).unwrap();
+            std::fs::write(&dest, b"new").unwrap();
+            // Dropped without commit.
+        }
+        assert_eq!(std::fs::read(&dest).unwrap(), b"old");
+        assert!(!backup_path(&dest).exists());
+    }
+
+    #[test]
+    fn commit_keeps_overwrite_and_discards_backup() {
+        let dir = temp_dir();
+        let dest = dir.join("binary");
+        std::fs::write(&dest, b"old").unwrap();
+        let mut transaction = InstallTransaction::new();
+        transaction.register(&dest).unwrap();
+        std::fs::write(&dest, b"new").unwrap();
+        transaction.commit();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"new");
+        assert!(!backup_path(&dest).exists());
+    }
+
+    #[test]
+    fn rollback_removes_freshly_created_directories() {
+        let dir = temp_dir();
+        let dest = dir.join("nested/deep/binary");
+        {
+            let mut transaction = InstallTransaction::new();
+            transaction.register(&dest).unwrap();
+            std::fs::create_dir_all(dest.parent().unwrap()).unwrap();
+            std::fs::write(&dest, b"new").unwrap();
+            // Dropped without commit.
+        }
+        assert!(!dest.exists());
+        assert!(!dir.join("nested").exists());
+    }
+}
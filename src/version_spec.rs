@@ -0,0 +1,176 @@
+// Copyright 2020 Sebastian Wiesner <sebastian@swsnr.de>
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Version requirements and channels for manifests.
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use versions::{Requirement, Versioning};
+
+/// The version a manifest tracks.
+///
+/// A manifest no longer pins a single version; instead its `info.version`
+/// field describes an acceptable target.  This follows nenv's `NodeVersion`:
+/// besides an exact version or a requirement range we allow the symbolic
+/// `latest` selector and named long-term-support channels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum VersionSpec {
+    /// Track the latest available version.
+    Latest,
+    /// Track a named long-term-support channel, e.g. `lts` or `fermium`.
+    Lts(String),
+    /// Track any version satisfying the requirement, e.g. `>=1.4, <2.0`.
+    Req(Requirement),
+    /// Track one exact version.
+    Exact(Versioning),
+}
+
+impl VersionSpec {
+    /// Whether `installed` satisfies this specification.
+    ///
+    /// An exact spec is satisfied by the very same version, a requirement by
+    /// any version in its range.  Symbolic selectors (`latest`, an LTS
+    /// channel) track a moving target that cannot be resolved from the
+    /// installed version alone, so the answer is unknown (`None`) and the
+    /// caller must resolve the channel to decide; returning `None` rather than
+    /// `true` keeps such tools from being silently treated as up to date.
+    pub fn satisfies(&self, installed: &Versioning) -> Option<bool> {
+        match self {
+            VersionSpec::Latest | VersionSpec::Lts(_) => None,
+            VersionSpec::Req(req) => Some(req.matches(installed)),
+            VersionSpec::Exact(version) => Some(version == installed),
+        }
+    }
+
+    /// Whether this spec tracks a moving target that needs external resolution.
+    pub fn is_moving(&self) -> bool {
+        matches!(self, VersionSpec::Latest | VersionSpec::Lts(_))
+    }
+}
+
+impl FromStr for VersionSpec {
+    type Err = Error;
+
+    /// Parse a `VersionSpec` from a manifest token.
+    ///
+    /// Reserve the literal `latest`, read a bare version (`1.4.0`) as an
+    /// [`Exact`](VersionSpec::Exact) pin and a token carrying a comparator
+    /// (`>=1.4, <2.0`, `^1`, `~1.4`) as a [`Requirement`], and fall back to
+    /// treating any other token as a channel/LTS label.
+    fn from_str(s: &str) -> Result<VersionSpec, Error> {
+        let token = s.trim();
+        if token.eq_ignore_ascii_case("latest") {
+            return Ok(VersionSpec::Latest);
+        }
+
+        let has_comparator =
+            token.starts_with(['<', '>', '=', '^', '~', '*']) || token.contains(',');
+        if has_comparator {
+            return Requirement::new(token)
+                .map(VersionSpec::Req)
+                .ok_or_else(|| anyhow::anyhow!("Invalid version requirement {:?}", token));
+        }
+
+        // A token that starts with a digit is a bare version, i.e. an exact
+        // pin; anything else (e.g. `fermium`) is a channel label.
+        let looks_like_version = token.starts_with(|c: char| c.is_ascii_digit());
+        match Versioning::new(token).filter(|_| looks_like_version) {
+            Some(version) => Ok(VersionSpec::Exact(version)),
+            None => Ok(VersionSpec::Lts(token.to_string())),
+        }
+    }
+}
+
+impl Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VersionSpec::Latest => f.write_str("latest"),
+            VersionSpec::Lts(channel) => f.write_str(channel),
+            VersionSpec::Req(req) => write!(f, "{}", req),
+            VersionSpec::Exact(version) => write!(f, "{}", version),
+        }
+    }
+}
+
+impl TryFrom<String> for VersionSpec {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<VersionSpec, Error> {
+        value.parse()
+    }
+}
+
+impl From<VersionSpec> for String {
+    fn from(spec: VersionSpec) -> String {
+        spec.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Versioning {
+        Versioning::new(s).unwrap()
+    }
+
+    #[test]
+    fn parse_latest_is_case_insensitive() {
+        assert_eq!("latest".parse::<VersionSpec>().unwrap(), VersionSpec::Latest);
+        assert_eq!("LATEST".parse::<VersionSpec>().unwrap(), VersionSpec::Latest);
+    }
+
+    #[test]
+    fn parse_bare_version_is_exact() {
+        assert_eq!(
+            "1.4.0".parse::<VersionSpec>().unwrap(),
+            VersionSpec::Exact(version("1.4.0"))
+        );
+    }
+
+    #[test]
+    fn parse_comparator_is_requirement() {
+        assert!(matches!(
+            ">=1.4, <2.0".parse::<VersionSpec>().unwrap(),
+            VersionSpec::Req(_)
+        ));
+    }
+
+    #[test]
+    fn parse_non_version_token_is_channel() {
+        assert_eq!(
+            "fermium".parse::<VersionSpec>().unwrap(),
+            VersionSpec::Lts("fermium".to_string())
+        );
+    }
+
+    #[test]
+    fn exact_satisfied_only_by_same_version() {
+        let spec = VersionSpec::Exact(version("1.4.0"));
+        assert_eq!(spec.satisfies(&version("1.4.0")), Some(true));
+        assert_eq!(spec.satisfies(&version("1.4.1")), Some(false));
+    }
+
+    #[test]
+    fn requirement_satisfied_within_range() {
+        let spec = ">=1.4, <2.0".parse::<VersionSpec>().unwrap();
+        assert_eq!(spec.satisfies(&version("1.5.0")), Some(true));
+        assert_eq!(spec.satisfies(&version("2.0.0")), Some(false));
+    }
+
+    #[test]
+    fn moving_targets_are_unknown() {
+        assert_eq!(VersionSpec::Latest.satisfies(&version("1.0.0")), None);
+        assert!(VersionSpec::Latest.is_moving());
+        let lts = VersionSpec::Lts("fermium".to_string());
+        assert_eq!(lts.satisfies(&version("1.0.0")), None);
+        assert!(lts.is_moving());
+    }
+}